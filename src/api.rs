@@ -11,6 +11,7 @@ use bp::{
 };
 use serde::Deserialize;
 use serde_with::hex::Hex;
+use sha2::{Digest, Sha256};
 
 #[serde_as]
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -58,6 +59,44 @@ pub struct MerkleProof {
     pub pos: usize,
 }
 
+impl MerkleProof {
+    /// Verifies that a transaction is included in a block with the given merkle root.
+    ///
+    /// Folds the standard Bitcoin merkle branch: starting from `txid`, each sibling
+    /// hash in `self.merkle` is combined in using `self.pos` to decide left/right
+    /// order, until a single root remains. Both the stored txids and `merkle_root`
+    /// are treated as raw 32-byte arrays in internal (little-endian) byte order --
+    /// the same order used on the wire -- so reverse them before displaying as the
+    /// familiar big-endian hex string.
+    ///
+    /// A proof with an empty `merkle` vector means `txid` is the sole coinbase of the
+    /// block, so `txid` itself must already equal `merkle_root`.
+    pub fn verify(&self, txid: Txid, merkle_root: Bytes32) -> bool {
+        let mut cur = txid.to_byte_array();
+        let mut index = self.pos;
+        for sibling in &self.merkle {
+            let h = sibling.to_byte_array();
+            let mut buf = [0u8; 64];
+            if index & 1 == 0 {
+                buf[..32].copy_from_slice(&cur);
+                buf[32..].copy_from_slice(&h);
+            } else {
+                buf[..32].copy_from_slice(&h);
+                buf[32..].copy_from_slice(&cur);
+            }
+            cur = sha256d(&buf);
+            index >>= 1;
+        }
+        cur == merkle_root.to_byte_array()
+    }
+
+    /// Convenience wrapper around [`MerkleProof::verify`] that takes the merkle root
+    /// straight from a [`BlockSummary`] instead of requiring the caller to extract it.
+    pub fn verify_against_block(&self, txid: Txid, block: &BlockSummary) -> bool {
+        self.verify(txid, block.merkle_root)
+    }
+}
+
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct OutputStatus {
     pub spent: bool,
@@ -112,6 +151,373 @@ pub struct BlockSummary {
     pub merkle_root: Bytes32,
 }
 
+impl BlockSummary {
+    /// Recomputes the merkle root from an ordered list of the block's transaction ids
+    /// and checks it against [`BlockSummary::merkle_root`], catching a lying or buggy
+    /// Esplora backend.
+    pub fn check_merkle_root(&self, txids: &[Txid]) -> bool {
+        compute_merkle_root(txids) == Some(self.merkle_root)
+    }
+}
+
+/// Recomputes a block's merkle root from an ordered list of transaction ids.
+///
+/// Implements the canonical Bitcoin algorithm on internal-byte-order 32-byte hashes:
+/// the txids form the leaf row; while the row has more than one entry, adjacent
+/// entries are paired and combined as `sha256d(left || right)`, duplicating the last
+/// entry when the row has an odd length, until a single hash remains.
+///
+/// Returns `None` for an empty list.
+///
+/// Note the well-known CVE-2012-2459: duplicating an odd row entry means two distinct
+/// transaction sets can fold to the same root, so a match here does not by itself
+/// prove the exact set of transactions, only that `txids` folds to the claimed root.
+pub fn compute_merkle_root(txids: &[Txid]) -> Option<Bytes32> {
+    let leaves: Vec<[u8; 32]> = txids.iter().map(Txid::to_byte_array).collect();
+    merkle_fold(&leaves).map(Bytes32::from_byte_array)
+}
+
+/// Folds a row of leaf hashes up to a single merkle root, duplicating the last entry
+/// of each odd-length row. Returns `None` for an empty slice.
+fn merkle_fold(leaves: &[[u8; 32]]) -> Option<[u8; 32]> {
+    if leaves.is_empty() {
+        return None;
+    }
+    let mut row = leaves.to_vec();
+    while row.len() > 1 {
+        if row.len() % 2 == 1 {
+            row.push(*row.last().expect("row is non-empty"));
+        }
+        row = row
+            .chunks(2)
+            .map(|pair| {
+                let mut buf = [0u8; 64];
+                buf[..32].copy_from_slice(&pair[0]);
+                buf[32..].copy_from_slice(&pair[1]);
+                sha256d(&buf)
+            })
+            .collect();
+    }
+    row.into_iter().next()
+}
+
+/// A parsed 80-byte Bitcoin block header.
+///
+/// Esplora's `BlockSummary`/`TxStatus` responses report a `block_hash` but never the
+/// raw header bytes backing it. Parsing the header (e.g. from a `/block/:hash/header`
+/// response) lets a client independently recompute the hash and check its
+/// proof-of-work instead of trusting the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub version: i32,
+    pub prev_blockhash: BlockHash,
+    pub merkle_root: Bytes32,
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    /// Parses a header from its 80-byte consensus (wire) encoding.
+    pub fn from_consensus_bytes(bytes: &[u8; 80]) -> Self {
+        BlockHeader {
+            version: i32::from_le_bytes(bytes[0..4].try_into().expect("4-byte slice")),
+            prev_blockhash: BlockHash::from_byte_array(
+                bytes[4..36].try_into().expect("32-byte slice"),
+            ),
+            merkle_root: Bytes32::from_byte_array(
+                bytes[36..68].try_into().expect("32-byte slice"),
+            ),
+            time: u32::from_le_bytes(bytes[68..72].try_into().expect("4-byte slice")),
+            bits: u32::from_le_bytes(bytes[72..76].try_into().expect("4-byte slice")),
+            nonce: u32::from_le_bytes(bytes[76..80].try_into().expect("4-byte slice")),
+        }
+    }
+
+    /// Re-encodes this header to its 80-byte consensus (wire) representation.
+    fn to_consensus_bytes(self) -> [u8; 80] {
+        let mut bytes = [0u8; 80];
+        bytes[0..4].copy_from_slice(&self.version.to_le_bytes());
+        bytes[4..36].copy_from_slice(&self.prev_blockhash.to_byte_array());
+        bytes[36..68].copy_from_slice(&self.merkle_root.to_byte_array());
+        bytes[68..72].copy_from_slice(&self.time.to_le_bytes());
+        bytes[72..76].copy_from_slice(&self.bits.to_le_bytes());
+        bytes[76..80].copy_from_slice(&self.nonce.to_le_bytes());
+        bytes
+    }
+
+    /// Returns the block hash: the double-SHA256 of the 80-byte consensus encoding.
+    pub fn block_hash(&self) -> BlockHash {
+        BlockHash::from_byte_array(sha256d(&self.to_consensus_bytes()))
+    }
+
+    /// Checks that [`BlockHeader::block_hash`] satisfies the proof-of-work target
+    /// encoded in `bits`.
+    ///
+    /// `bits` is decoded per the compact ("nBits") format: `exponent = bits >> 24`,
+    /// `mantissa = bits & 0x007f_ffff`, and `target = mantissa << (8 * (exponent -
+    /// 3))` as a 256-bit integer (an `exponent <= 3` right-shifts instead). The block
+    /// hash is interpreted as a little-endian 256-bit integer and must be `<= target`.
+    ///
+    /// Returns `false` without comparing hashes if `bits` encodes a negative or
+    /// overflowing target (see [`BlockHeader::expand_target`]) -- consensus treats
+    /// both as unconditionally invalid, same as Bitcoin Core's `arith_uint256`.
+    pub fn validate_pow(&self) -> bool {
+        let Some(target) = Self::expand_target(self.bits) else {
+            return false;
+        };
+        let hash = self.block_hash().to_byte_array();
+        // Both are little-endian 256-bit integers: the most significant byte is last.
+        for i in (0..32).rev() {
+            match hash[i].cmp(&target[i]) {
+                std::cmp::Ordering::Less => return true,
+                std::cmp::Ordering::Greater => return false,
+                std::cmp::Ordering::Equal => {}
+            }
+        }
+        true
+    }
+
+    /// Expands the compact `bits` encoding into a little-endian 256-bit target, or
+    /// `None` for a negative (sign bit set) or overflowing target.
+    fn expand_target(bits: u32) -> Option<[u8; 32]> {
+        let exponent = bits >> 24;
+        let mantissa = bits & 0x007f_ffff;
+        if mantissa != 0 {
+            let is_negative = bits & 0x0080_0000 != 0;
+            let overflows = exponent > 34
+                || (mantissa > 0xff && exponent > 33)
+                || (mantissa > 0xffff && exponent > 32);
+            if is_negative || overflows {
+                return None;
+            }
+        }
+        let mut target = [0u8; 32];
+        if exponent <= 3 {
+            let value = mantissa >> (8 * (3 - exponent));
+            target[0..4].copy_from_slice(&value.to_le_bytes());
+        } else {
+            let offset = (exponent - 3) as usize;
+            for (i, byte) in mantissa.to_le_bytes().into_iter().enumerate() {
+                if offset + i < target.len() {
+                    target[offset + i] = byte;
+                }
+            }
+        }
+        Some(target)
+    }
+
+    /// Parses a Core-style `merkleblock` hex blob -- the output of `gettxoutproof` /
+    /// `verifytxoutproof` -- into its header and the set of matched transaction ids.
+    ///
+    /// The blob is the 80-byte header, a `u32` transaction count, a varint-prefixed
+    /// vector of hashes, and a varint-prefixed bit-vector of flags, encoding a partial
+    /// merkle tree traversed from the root (see [`PartialMerkleTree::traverse`]). The
+    /// computed root must match the header's `merkle_root`, every hash supplied must
+    /// be consumed by the traversal, and so must every flag byte; unused padding bits
+    /// in the last flag byte are not checked.
+    pub fn from_merkleblock_hex(hex: &str) -> Result<(BlockHeader, Vec<Txid>), MerkleBlockError> {
+        let bytes = Vec::<u8>::from_hex(hex).map_err(|_| MerkleBlockError::InvalidHex)?;
+        let mut reader = BytesReader::new(&bytes);
+
+        let header_bytes: [u8; 80] = reader.take(80)?.try_into().expect("took 80 bytes");
+        let header = BlockHeader::from_consensus_bytes(&header_bytes);
+
+        let tx_count = reader.take_u32_le()? as usize;
+        if tx_count == 0 {
+            return Err(MerkleBlockError::EmptyTransactionSet);
+        }
+        // Bound the hash/flag counts against what's actually left in the buffer
+        // before sizing anything, so a forged varint can't force a large allocation.
+        let hash_count = reader.take_varint()? as usize;
+        if hash_count > reader.remaining() / 32 {
+            return Err(MerkleBlockError::UnexpectedEof);
+        }
+        let mut hashes = Vec::with_capacity(hash_count);
+        for _ in 0..hash_count {
+            hashes.push(reader.take(32)?.try_into().expect("took 32 bytes"));
+        }
+        let flag_byte_count = reader.take_varint()? as usize;
+        if flag_byte_count > reader.remaining() {
+            return Err(MerkleBlockError::UnexpectedEof);
+        }
+        let flags = reader.take(flag_byte_count)?;
+
+        if reader.remaining() != 0 {
+            return Err(MerkleBlockError::TrailingData);
+        }
+
+        let mut pmt = PartialMerkleTree { hashes: &hashes, hash_pos: 0, flags, flag_pos: 0, tx_count };
+        let mut matched = Vec::new();
+        let root = pmt.traverse(pmt.height(), 0, &mut matched)?;
+
+        if pmt.hash_pos != hashes.len() || flags.len() != (pmt.flag_pos + 7) / 8 {
+            return Err(MerkleBlockError::TrailingData);
+        }
+        if root != header.merkle_root.to_byte_array() {
+            return Err(MerkleBlockError::RootMismatch);
+        }
+
+        let txids = matched.into_iter().map(Txid::from_byte_array).collect();
+        Ok((header, txids))
+    }
+}
+
+/// Errors returned by [`BlockHeader::from_merkleblock_hex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MerkleBlockError {
+    /// the blob is not valid hex.
+    InvalidHex,
+    /// the blob ends before its own length-prefixed fields are fully read.
+    UnexpectedEof,
+    /// the blob has unused hashes, flag bits, or trailing bytes left over after the
+    /// partial merkle tree traversal.
+    TrailingData,
+    /// the partial merkle tree's computed root does not match the header's
+    /// `merkle_root`.
+    RootMismatch,
+    /// an internal node's right child duplicates a present (not absent) left child.
+    DuplicateBranch,
+    /// the blob claims zero transactions, which cannot form a tree.
+    EmptyTransactionSet,
+}
+
+impl std::fmt::Display for MerkleBlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            MerkleBlockError::InvalidHex => "the blob is not valid hex",
+            MerkleBlockError::UnexpectedEof => {
+                "the blob ends before its own length-prefixed fields are fully read"
+            }
+            MerkleBlockError::TrailingData => {
+                "the blob has unused hashes, flag bits, or trailing bytes"
+            }
+            MerkleBlockError::RootMismatch => {
+                "the computed merkle root does not match the header's merkle_root"
+            }
+            MerkleBlockError::DuplicateBranch => {
+                "an internal node's right child duplicates a present left child"
+            }
+            MerkleBlockError::EmptyTransactionSet => "the blob claims zero transactions",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for MerkleBlockError {}
+
+/// A cursor over a byte slice used to parse the length-prefixed fields of a
+/// `merkleblock` blob.
+struct BytesReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BytesReader<'a> {
+    fn new(data: &'a [u8]) -> Self { BytesReader { data, pos: 0 } }
+
+    fn remaining(&self) -> usize { self.data.len() - self.pos }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], MerkleBlockError> {
+        if self.remaining() < n {
+            return Err(MerkleBlockError::UnexpectedEof);
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn take_u32_le(&mut self) -> Result<u32, MerkleBlockError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("took 4 bytes")))
+    }
+
+    /// Reads a Bitcoin `CompactSize` varint.
+    fn take_varint(&mut self) -> Result<u64, MerkleBlockError> {
+        match self.take(1)?[0] {
+            0xfd => Ok(u16::from_le_bytes(self.take(2)?.try_into().expect("took 2 bytes")) as u64),
+            0xfe => Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("took 4 bytes")) as u64),
+            0xff => Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("took 8 bytes"))),
+            n => Ok(n as u64),
+        }
+    }
+}
+
+/// State for walking a Core-style partial merkle tree, as produced by
+/// `gettxoutproof`/`verifytxoutproof`.
+struct PartialMerkleTree<'a> {
+    hashes: &'a [[u8; 32]],
+    hash_pos: usize,
+    flags: &'a [u8],
+    flag_pos: usize,
+    tx_count: usize,
+}
+
+impl<'a> PartialMerkleTree<'a> {
+    /// The tree height: the smallest height at which the tree's width is 1.
+    fn height(&self) -> usize {
+        let mut height = 0;
+        while Self::tree_width(self.tx_count.max(1), height) > 1 {
+            height += 1;
+        }
+        height
+    }
+
+    /// The number of nodes at a given height, per Bitcoin Core's
+    /// `CPartialMerkleTree::CalcTreeWidth`.
+    fn tree_width(tx_count: usize, height: usize) -> usize { (tx_count + (1 << height) - 1) >> height }
+
+    fn next_flag(&mut self) -> Result<bool, MerkleBlockError> {
+        if self.flag_pos >= self.flags.len() * 8 {
+            return Err(MerkleBlockError::UnexpectedEof);
+        }
+        let bit = (self.flags[self.flag_pos / 8] >> (self.flag_pos % 8)) & 1 == 1;
+        self.flag_pos += 1;
+        Ok(bit)
+    }
+
+    fn next_hash(&mut self) -> Result<[u8; 32], MerkleBlockError> {
+        let hash = *self.hashes.get(self.hash_pos).ok_or(MerkleBlockError::UnexpectedEof)?;
+        self.hash_pos += 1;
+        Ok(hash)
+    }
+
+    /// Recursively walks the node at `(height, pos)`, pushing matched leaf hashes into
+    /// `matched`, and returns that node's hash.
+    fn traverse(
+        &mut self,
+        height: usize,
+        pos: usize,
+        matched: &mut Vec<[u8; 32]>,
+    ) -> Result<[u8; 32], MerkleBlockError> {
+        let interesting = self.next_flag()?;
+        if height == 0 || !interesting {
+            let hash = self.next_hash()?;
+            if height == 0 && interesting {
+                matched.push(hash);
+            }
+            return Ok(hash);
+        }
+        let left = self.traverse(height - 1, pos * 2, matched)?;
+        let right = if pos * 2 + 1 < Self::tree_width(self.tx_count, height - 1) {
+            let right = self.traverse(height - 1, pos * 2 + 1, matched)?;
+            // A right child is only allowed to duplicate `left` when it was *absent*
+            // (handled in the `else` branch below); a distinct right child that
+            // happens to equal `left` is the CVE-2012-2459 malleability and must be
+            // rejected, not silently accepted as an alternate valid proof.
+            if right == left {
+                return Err(MerkleBlockError::DuplicateBranch);
+            }
+            right
+        } else {
+            left
+        };
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&left);
+        buf[32..].copy_from_slice(&right);
+        Ok(sha256d(&buf))
+    }
+}
+
 /// Address statistics, includes the address, and the utxo information for the address.
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
 pub struct AddressStats {
@@ -182,6 +588,60 @@ impl Tx {
             })
             .collect()
     }
+
+    /// Validates a block's SegWit witness commitment against its coinbase transaction.
+    ///
+    /// `wtxids` is the ordered list of wtxids for every transaction in the block,
+    /// including the coinbase (whose own wtxid is defined as all-zero and is
+    /// overwritten as such here regardless of what's passed in).
+    ///
+    /// Finds the last `scriptpubkey` in `coinbase.vout` that starts with the witness
+    /// commitment magic `6a24aa21a9ed`. Unlike Bitcoin Core's `GetWitnessCommitmentIndex`,
+    /// an output is picked by its prefix alone, with no minimum script length; a picked
+    /// output with fewer than 32 bytes after the magic fails the check.
+    /// Returns `true` immediately if no such output exists -- nothing in the block
+    /// claims to commit to witness data. Otherwise the witness merkle root is folded
+    /// over `wtxids`, hashed together with the coinbase's witness reserved value, and
+    /// compared against the commitment bytes. Returns `false` on any mismatch.
+    pub fn check_witness_commitment(coinbase: &Tx, wtxids: &[Bytes32]) -> bool {
+        let Some(commitment_bytes) = coinbase.vout.iter().rev().find_map(|vout| {
+            let script: &[u8] = vout.scriptpubkey.as_ref();
+            script.strip_prefix(WITNESS_COMMITMENT_MAGIC.as_slice())
+        }) else {
+            return true;
+        };
+
+        let mut leaves: Vec<[u8; 32]> = wtxids.iter().map(Bytes32::to_byte_array).collect();
+        if let Some(coinbase_leaf) = leaves.first_mut() {
+            *coinbase_leaf = [0u8; 32];
+        }
+        let Some(witness_root) = merkle_fold(&leaves) else {
+            return false;
+        };
+
+        let Some(reserved) = coinbase.vin.first().and_then(|vin| vin.witness.first()) else {
+            return false;
+        };
+        if reserved.len() != 32 {
+            return false;
+        }
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&witness_root);
+        buf[32..].copy_from_slice(reserved);
+        let commitment = sha256d(&buf);
+
+        commitment_bytes.get(..32) == Some(commitment.as_slice())
+    }
+}
+
+/// The 6-byte prefix identifying a witness commitment output in a coinbase
+/// transaction, per BIP 141: `OP_RETURN OP_PUSHBYTES_36 <commitment-header>`.
+const WITNESS_COMMITMENT_MAGIC: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// Bitcoin's double-SHA256: `SHA256(SHA256(data))`.
+fn sha256d(data: &[u8]) -> [u8; 32] {
+    let once = Sha256::digest(data);
+    Sha256::digest(once).into()
 }
 
 fn deserialize_witness<'de, D>(d: D) -> Result<Vec<Vec<u8>>, D::Error>
@@ -194,3 +654,383 @@ where
         .collect::<Result<Vec<Vec<u8>>, _>>()
         .map_err(serde::de::Error::custom)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn txid(byte: u8) -> Txid { Txid::from_byte_array([byte; 32]) }
+
+    #[test]
+    fn compute_merkle_root_returns_none_for_an_empty_list() {
+        assert_eq!(compute_merkle_root(&[]), None);
+    }
+
+    #[test]
+    fn compute_merkle_root_of_a_single_txid_is_that_txid() {
+        let only = txid(0);
+        assert_eq!(compute_merkle_root(&[only]), Some(Bytes32::from_byte_array(only.to_byte_array())));
+    }
+
+    #[test]
+    fn compute_merkle_root_matches_a_hand_folded_even_row() {
+        let txids = [txid(0), txid(1)];
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&txids[0].to_byte_array());
+        buf[32..].copy_from_slice(&txids[1].to_byte_array());
+        let expected = Bytes32::from_byte_array(sha256d(&buf));
+
+        assert_eq!(compute_merkle_root(&txids), Some(expected));
+    }
+
+    #[test]
+    fn compute_merkle_root_duplicates_the_last_entry_of_an_odd_row() {
+        let txids = [txid(0), txid(1), txid(2)];
+        let with_duplicate = [txid(0), txid(1), txid(2), txid(2)];
+        assert_eq!(compute_merkle_root(&txids), compute_merkle_root(&with_duplicate));
+    }
+
+    #[test]
+    fn check_merkle_root_accepts_a_matching_set_and_rejects_a_tampered_one() {
+        let txids = vec![txid(0), txid(1), txid(2)];
+        let root = compute_merkle_root(&txids).expect("non-empty");
+        let block = BlockSummary {
+            id: BlockHash::from_byte_array([0u8; 32]),
+            time: BlockTime { timestamp: 0, height: 0 },
+            previousblockhash: None,
+            merkle_root: root,
+        };
+
+        assert!(block.check_merkle_root(&txids));
+        assert!(!block.check_merkle_root(&[txid(0), txid(1)]));
+    }
+
+    #[test]
+    fn merkle_proof_verify_accepts_a_valid_branch() {
+        let leaf = txid(0);
+        let sibling = txid(1);
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&leaf.to_byte_array());
+        buf[32..].copy_from_slice(&sibling.to_byte_array());
+        let root = Bytes32::from_byte_array(sha256d(&buf));
+
+        let proof = MerkleProof { block_height: 0, merkle: vec![sibling], pos: 0 };
+        assert!(proof.verify(leaf, root));
+    }
+
+    #[test]
+    fn merkle_proof_verify_rejects_a_branch_for_the_wrong_txid() {
+        let leaf = txid(0);
+        let sibling = txid(1);
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&leaf.to_byte_array());
+        buf[32..].copy_from_slice(&sibling.to_byte_array());
+        let root = Bytes32::from_byte_array(sha256d(&buf));
+
+        let proof = MerkleProof { block_height: 0, merkle: vec![sibling], pos: 0 };
+        assert!(!proof.verify(txid(2), root));
+    }
+
+    #[test]
+    fn merkle_proof_verify_respects_pos_for_left_right_ordering() {
+        let leaf = txid(0);
+        let sibling = txid(1);
+        let mut buf = [0u8; 64];
+        // pos = 1 means `leaf` is the right child, so the sibling goes first.
+        buf[..32].copy_from_slice(&sibling.to_byte_array());
+        buf[32..].copy_from_slice(&leaf.to_byte_array());
+        let root = Bytes32::from_byte_array(sha256d(&buf));
+
+        let proof = MerkleProof { block_height: 0, merkle: vec![sibling], pos: 1 };
+        assert!(proof.verify(leaf, root));
+        // The same sibling/root folded as if `leaf` were the left child must fail.
+        let wrong_proof = MerkleProof { block_height: 0, merkle: vec![sibling], pos: 0 };
+        assert!(!wrong_proof.verify(leaf, root));
+    }
+
+    #[test]
+    fn merkle_proof_verify_handles_a_sole_coinbase_block() {
+        let leaf = txid(0);
+        let root = Bytes32::from_byte_array(leaf.to_byte_array());
+        let proof = MerkleProof { block_height: 0, merkle: vec![], pos: 0 };
+        assert!(proof.verify(leaf, root));
+        assert!(!proof.verify(txid(1), root));
+    }
+
+    #[test]
+    fn merkle_proof_verify_against_block_uses_the_blocks_merkle_root() {
+        let leaf = txid(0);
+        let root = Bytes32::from_byte_array(leaf.to_byte_array());
+        let block = BlockSummary {
+            id: BlockHash::from_byte_array([0u8; 32]),
+            time: BlockTime { timestamp: 0, height: 0 },
+            previousblockhash: None,
+            merkle_root: root,
+        };
+        let proof = MerkleProof { block_height: 0, merkle: vec![], pos: 0 };
+        assert!(proof.verify_against_block(leaf, &block));
+    }
+
+    fn header_with(bits: u32, nonce: u32) -> BlockHeader {
+        BlockHeader {
+            version: 1,
+            prev_blockhash: BlockHash::from_byte_array([0u8; 32]),
+            merkle_root: Bytes32::from_byte_array([0x11u8; 32]),
+            time: 1_700_000_000,
+            bits,
+            nonce,
+        }
+    }
+
+    #[test]
+    fn expand_target_matches_the_mainnet_minimum_difficulty_bits() {
+        let target = BlockHeader::expand_target(0x1d00ffff).expect("valid target");
+        // exponent 29, mantissa 0x00ffff -> mantissa bytes sit at offset 26.
+        assert_eq!(&target[26..29], &[0xff, 0xff, 0x00]);
+        assert_eq!(&target[..26], &[0u8; 26]);
+        assert_eq!(&target[29..], &[0u8; 3]);
+    }
+
+    #[test]
+    fn expand_target_rejects_the_sign_bit() {
+        // exponent 1, mantissa 1, sign bit (0x0080_0000) set.
+        assert_eq!(BlockHeader::expand_target(0x0180_0001), None);
+    }
+
+    #[test]
+    fn expand_target_rejects_an_overflowing_exponent() {
+        // exponent 35 would shift the mantissa entirely past the 32-byte target.
+        assert_eq!(BlockHeader::expand_target(0x2300_0001), None);
+    }
+
+    #[test]
+    fn validate_pow_rejects_a_header_with_an_invalid_target_regardless_of_hash() {
+        let header = header_with(0x0180_0001, 0);
+        assert!(!header.validate_pow());
+    }
+
+    #[test]
+    fn validate_pow_accepts_a_header_whose_hash_is_under_an_easy_target() {
+        // bits = 0x207fffff is the near-maximal (easiest) representable target; nonce 0
+        // happens to satisfy it for this fixed header.
+        let header = header_with(0x207f_ffff, 0);
+        assert!(header.validate_pow());
+    }
+
+    #[test]
+    fn validate_pow_rejects_a_header_whose_hash_is_over_an_easy_target() {
+        let header = header_with(0x207f_ffff, 1);
+        assert!(!header.validate_pow());
+    }
+
+    fn dummy_status() -> TxStatus {
+        TxStatus { confirmed: false, block_height: None, block_hash: None, block_time: None }
+    }
+
+    fn coinbase_with(vin_witness: Vec<Vec<u8>>, vout: Vec<Vout>) -> Tx {
+        Tx {
+            txid: txid(0xc0),
+            version: 1,
+            locktime: 0,
+            vin: vec![Vin {
+                txid: Txid::from_byte_array([0u8; 32]),
+                vout: 0xffff_ffff,
+                prevout: None,
+                scriptsig: SigScript::try_from(Vec::new()).expect("empty script is valid"),
+                witness: vin_witness,
+                sequence: 0xffff_ffff,
+                is_coinbase: true,
+            }],
+            vout,
+            size: 0,
+            weight: 0,
+            status: dummy_status(),
+            fee: 0,
+        }
+    }
+
+    /// Builds the varint-prefixed `merkleblock` encoding used by `gettxoutproof`.
+    fn build_merkleblock_hex(
+        header_bytes: [u8; 80],
+        tx_count: u32,
+        hashes: &[[u8; 32]],
+        flag_byte: u8,
+    ) -> String {
+        let mut blob = Vec::new();
+        blob.extend_from_slice(&header_bytes);
+        blob.extend_from_slice(&tx_count.to_le_bytes());
+        blob.push(hashes.len() as u8); // varint, small enough to fit in one byte
+        for h in hashes {
+            blob.extend_from_slice(h);
+        }
+        blob.push(1); // flag byte count varint
+        blob.push(flag_byte);
+        blob.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn from_merkleblock_hex_rejects_a_zero_transaction_count() {
+        let mut blob = vec![0u8; 80];
+        blob.extend_from_slice(&0u32.to_le_bytes());
+        blob.push(0); // hash_count
+        blob.push(0); // flag_byte_count
+        let hex: String = blob.iter().map(|b| format!("{:02x}", b)).collect();
+
+        assert_eq!(
+            BlockHeader::from_merkleblock_hex(&hex),
+            Err(MerkleBlockError::EmptyTransactionSet)
+        );
+    }
+
+    #[test]
+    fn from_merkleblock_hex_rejects_oversized_hash_count_without_allocating() {
+        // An honest 80-byte header + tx_count, followed by a hash-count varint
+        // (0xff prefix) claiming ~a billion hashes with no data behind it. If this
+        // allocated eagerly it would try to reserve ~32GB; instead it must bail out
+        // with `UnexpectedEof` before ever sizing a `Vec`.
+        let mut blob = vec![0u8; 80];
+        blob.extend_from_slice(&3u32.to_le_bytes());
+        blob.push(0xff);
+        blob.extend_from_slice(&1_000_000_000u64.to_le_bytes());
+        let hex: String = blob.iter().map(|b| format!("{:02x}", b)).collect();
+
+        assert_eq!(
+            BlockHeader::from_merkleblock_hex(&hex),
+            Err(MerkleBlockError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn from_merkleblock_hex_rejects_oversized_flag_byte_count() {
+        let mut blob = vec![0u8; 80];
+        blob.extend_from_slice(&1u32.to_le_bytes());
+        blob.push(1); // hash_count = 1
+        blob.extend_from_slice(&[0u8; 32]);
+        blob.push(0xfe);
+        blob.extend_from_slice(&1_000_000u32.to_le_bytes());
+        let hex: String = blob.iter().map(|b| format!("{:02x}", b)).collect();
+
+        assert_eq!(
+            BlockHeader::from_merkleblock_hex(&hex),
+            Err(MerkleBlockError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn from_merkleblock_hex_round_trips_a_fully_matched_three_leaf_tree() {
+        let txids = [txid(0), txid(1), txid(2)];
+        let leaves: Vec<[u8; 32]> = txids.iter().map(Txid::to_byte_array).collect();
+        let root = merkle_fold(&leaves).expect("non-empty");
+
+        let mut header_bytes = [0u8; 80];
+        header_bytes[36..68].copy_from_slice(&root);
+
+        // Traversal of a match-all 3-leaf (height-2) tree visits 6 nodes -- the root,
+        // its two height-1 children, the two leaves under the first, and the single
+        // (duplicated) leaf under the second -- so 6 flag bits are consumed, all set.
+        let hex = build_merkleblock_hex(header_bytes, 3, &leaves, 0b0011_1111);
+
+        let (header, matched) =
+            BlockHeader::from_merkleblock_hex(&hex).expect("well-formed proof");
+        assert_eq!(header.merkle_root.to_byte_array(), root);
+        assert_eq!(matched, txids);
+    }
+
+    #[test]
+    fn from_merkleblock_hex_rejects_duplicate_right_child_malleability() {
+        // Four leaves where the last two are identical: the height-1 node covering
+        // them has two *present* children (tree_width(4, 0) == 4, so no legitimate
+        // duplication applies) that happen to hash equal -- the CVE-2012-2459
+        // substitution this traversal must reject rather than silently accept.
+        let txids = [txid(0), txid(1), txid(2), txid(2)];
+        let leaves: Vec<[u8; 32]> = txids.iter().map(Txid::to_byte_array).collect();
+        let root = merkle_fold(&leaves).expect("non-empty");
+
+        let mut header_bytes = [0u8; 80];
+        header_bytes[36..68].copy_from_slice(&root);
+
+        // Match everything: height 2, 4 leaves, 7 tree nodes -> 7 flag bits, all set.
+        let hex = build_merkleblock_hex(header_bytes, 4, &leaves, 0b0111_1111);
+
+        assert_eq!(
+            BlockHeader::from_merkleblock_hex(&hex),
+            Err(MerkleBlockError::DuplicateBranch)
+        );
+    }
+
+    #[test]
+    fn check_witness_commitment_true_when_no_commitment_output_exists() {
+        let coinbase = coinbase_with(vec![], vec![]);
+        assert!(Tx::check_witness_commitment(&coinbase, &[Bytes32::from_byte_array([1u8; 32])]));
+    }
+
+    #[test]
+    fn check_witness_commitment_still_validates_when_coinbase_witness_is_empty() {
+        // A lying/buggy backend can zero out the coinbase's own witness field, but
+        // that must not bypass validation of a commitment output that's actually
+        // present -- the decision is driven by `coinbase.vout`, not `coinbase.vin`.
+        let wtxids = [Bytes32::from_byte_array([0u8; 32]), Bytes32::from_byte_array([1u8; 32])];
+        let mut leaves: Vec<[u8; 32]> = wtxids.iter().map(Bytes32::to_byte_array).collect();
+        leaves[0] = [0u8; 32];
+        let witness_root = merkle_fold(&leaves).expect("non-empty");
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&witness_root);
+        buf[32..].copy_from_slice(&[0xabu8; 32]);
+        let commitment = sha256d(&buf);
+
+        let mut script = WITNESS_COMMITMENT_MAGIC.to_vec();
+        script.extend_from_slice(&commitment);
+        let vout = Vout { value: 0, scriptpubkey: ScriptPubkey::try_from(script).expect("valid") };
+        let coinbase = coinbase_with(vec![], vec![vout]);
+
+        assert!(!Tx::check_witness_commitment(&coinbase, &wtxids));
+    }
+
+    #[test]
+    fn check_witness_commitment_accepts_a_correctly_computed_commitment() {
+        let wtxids = [Bytes32::from_byte_array([0u8; 32]), Bytes32::from_byte_array([1u8; 32])];
+        let mut leaves: Vec<[u8; 32]> = wtxids.iter().map(Bytes32::to_byte_array).collect();
+        leaves[0] = [0u8; 32]; // coinbase wtxid is defined as all-zero
+        let witness_root = merkle_fold(&leaves).expect("non-empty");
+        let reserved = [0xabu8; 32];
+        let mut buf = [0u8; 64];
+        buf[..32].copy_from_slice(&witness_root);
+        buf[32..].copy_from_slice(&reserved);
+        let commitment = sha256d(&buf);
+
+        let mut script = WITNESS_COMMITMENT_MAGIC.to_vec();
+        script.extend_from_slice(&commitment);
+        let vout = Vout {
+            value: 0,
+            scriptpubkey: ScriptPubkey::try_from(script).expect("valid script"),
+        };
+        let coinbase = coinbase_with(vec![reserved.to_vec()], vec![vout]);
+
+        assert!(Tx::check_witness_commitment(&coinbase, &wtxids));
+    }
+
+    #[test]
+    fn check_witness_commitment_rejects_a_mismatched_commitment() {
+        let wtxids = [Bytes32::from_byte_array([0u8; 32]), Bytes32::from_byte_array([1u8; 32])];
+        let reserved = [0xabu8; 32];
+        let mut script = WITNESS_COMMITMENT_MAGIC.to_vec();
+        script.extend_from_slice(&[0u8; 32]); // wrong commitment bytes
+        let vout = Vout {
+            value: 0,
+            scriptpubkey: ScriptPubkey::try_from(script).expect("valid script"),
+        };
+        let coinbase = coinbase_with(vec![reserved.to_vec()], vec![vout]);
+
+        assert!(!Tx::check_witness_commitment(&coinbase, &wtxids));
+    }
+
+    #[test]
+    fn check_witness_commitment_true_when_no_vout_matches_the_magic() {
+        let wtxids = [Bytes32::from_byte_array([0u8; 32]), Bytes32::from_byte_array([1u8; 32])];
+        let reserved = [0xabu8; 32];
+        let vout = Vout { value: 0, scriptpubkey: ScriptPubkey::try_from(vec![]).expect("valid") };
+        let coinbase = coinbase_with(vec![reserved.to_vec()], vec![vout]);
+
+        assert!(Tx::check_witness_commitment(&coinbase, &wtxids));
+    }
+}